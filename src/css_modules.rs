@@ -13,7 +13,7 @@ use crate::printer::Printer;
 use crate::properties::css_modules::{Composes, ComposesFrom};
 use crate::selector::Selectors;
 use crate::traits::ToCss;
-use cssparser::serialize_identifier;
+use cssparser::{serialize_identifier, Parser, ParserInput, Token};
 use data_encoding::{Encoding, Specification};
 use lazy_static::lazy_static;
 use parcel_selectors::SelectorList;
@@ -41,22 +41,36 @@ pub struct Pattern<'i> {
 impl<'i> Default for Pattern<'i> {
   fn default() -> Self {
     Pattern {
-      segments: smallvec![Segment::Hash, Segment::Literal("_"), Segment::Local],
+      segments: smallvec![Segment::Hash(None), Segment::Literal("_"), Segment::Local],
     }
   }
 }
 
 impl<'i> Pattern<'i> {
-  /// dopifhdoifhdofih
+  /// Parses a pattern from a string.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use lightningcss::css_modules::Pattern;
+  ///
+  /// let pattern = Pattern::parse("[folder]_[name]__[local]_[hash:5]").unwrap();
+  /// ```
   pub fn parse(mut input: &'i str) -> Result<Self, ()> {
     let mut segments = SmallVec::new();
     while !input.is_empty() {
       if input.starts_with('[') {
         if let Some(end_idx) = input.find(']') {
-          let segment = match &input[0..=end_idx] {
-            "[name]" => Segment::Name,
-            "[local]" => Segment::Local,
-            "[hash]" => Segment::Hash,
+          let contents = &input[1..end_idx];
+          let segment = match contents {
+            "name" => Segment::Name,
+            "folder" => Segment::Folder,
+            "local" => Segment::Local,
+            "hash" => Segment::Hash(None),
+            _ if contents.starts_with("hash:") => {
+              let len: usize = contents["hash:".len()..].parse().map_err(|_| ())?;
+              Segment::Hash(Some(len))
+            }
             _ => return Err(()),
           };
           segments.push(segment);
@@ -74,37 +88,51 @@ impl<'i> Pattern<'i> {
     Ok(Pattern { segments })
   }
 
-  /// dpofihdoifhd
-  pub fn write<W, E>(&self, hash: &str, local: &str, mut write: W) -> Result<(), E>
+  /// Writes this pattern to the given output, interpolating the hash, the path of the
+  /// source file that `local` was declared in, and the local (original) name.
+  pub fn write<W, E>(&self, hash: &str, path: &Path, local: &str, mut write: W) -> Result<(), E>
   where
     W: FnMut(&str) -> Result<(), E>,
   {
     for segment in &self.segments {
-      // segment.write(css_module, local, dest)?;
       match segment {
         Segment::Literal(s) => {
           write(s)?;
         }
-        // Segment::Name => {
-        //   let name = dest.filename();
-        //   let path = Path::new(name);
-        //   let basename = path.file_name().map(|name| name.split('.'));
-        // }
+        Segment::Name => {
+          if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            write(stem)?;
+          }
+        }
+        Segment::Folder => {
+          if let Some(folder) = path.parent().and_then(|parent| parent.file_name()).and_then(|name| name.to_str()) {
+            write(folder)?;
+          }
+        }
         Segment::Local => {
           write(local)?;
         }
-        Segment::Hash => {
+        Segment::Hash(len) => {
+          let hash = match len {
+            Some(len) => &hash[0..(*len).min(hash.len())],
+            None => hash,
+          };
+
+          // Ensure the truncated hash doesn't start with a digit, since it may be used as an identifier.
+          if matches!(hash.as_bytes().first(), Some(b'0'..=b'9')) {
+            write("_")?;
+          }
+
           write(hash)?;
         }
-        _ => todo!(),
       }
     }
     Ok(())
   }
 
-  fn write_to_string(&self, hash: &str, local: &str) -> Result<String, std::fmt::Error> {
+  fn write_to_string(&self, hash: &str, path: &Path, local: &str) -> Result<String, std::fmt::Error> {
     let mut res = String::new();
-    self.write(hash, local, |s| res.write_str(s))?;
+    self.write(hash, path, local, |s| res.write_str(s))?;
     Ok(res)
   }
 }
@@ -116,12 +144,14 @@ impl<'i> Pattern<'i> {
 pub enum Segment<'i> {
   /// A literal string segment.
   Literal(&'i str),
-  /// The base file name.
+  /// The base file name, with its extension removed.
   Name,
+  /// The name of the folder that the source file is in.
+  Folder,
   /// The original class name.
   Local,
-  /// A hash of the file name.
-  Hash,
+  /// A hash of the file name, optionally truncated to the given number of characters.
+  Hash(Option<usize>),
 }
 
 /// A referenced name within a CSS module, e.g. via the `composes` property.
@@ -149,6 +179,67 @@ pub enum CssModuleReference {
   },
 }
 
+/// A parsed ICSS `@value` rule prelude.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ValueRule<'i> {
+  /// `@value name: <token stream>;` declares `name` as a locally-scoped constant with the
+  /// given raw, serialized value.
+  Declaration {
+    /// The declared name.
+    name: &'i str,
+    /// The raw, serialized value, substituted wherever `name` is later referenced.
+    value: &'i str,
+  },
+  /// `@value a, b from "./other.css";` imports one or more values from another file.
+  Import {
+    /// The imported names.
+    names: Vec<&'i str>,
+    /// The dependency specifier of the file they are imported from.
+    specifier: &'i str,
+  },
+}
+
+impl<'i> ValueRule<'i> {
+  /// Parses the prelude of an `@value` rule, i.e. the text between `@value` and the
+  /// terminating `;`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use lightningcss::css_modules::ValueRule;
+  ///
+  /// assert_eq!(
+  ///   ValueRule::parse("small: 480px"),
+  ///   Some(ValueRule::Declaration { name: "small", value: "480px" })
+  /// );
+  /// ```
+  pub fn parse(prelude: &'i str) -> Option<Self> {
+    let prelude = prelude.trim();
+    if let Some(from_idx) = prelude.rfind(" from ") {
+      let names: Vec<&str> = prelude[..from_idx]
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .collect();
+      let specifier = prelude[from_idx + " from ".len()..].trim().trim_matches(|c| c == '"' || c == '\'');
+      if names.is_empty() || specifier.is_empty() {
+        return None;
+      }
+
+      return Some(ValueRule::Import { names, specifier });
+    }
+
+    let colon_idx = prelude.find(':')?;
+    let name = prelude[..colon_idx].trim();
+    let value = prelude[colon_idx + 1..].trim();
+    if name.is_empty() || value.is_empty() {
+      return None;
+    }
+
+    Some(ValueRule::Declaration { name, value })
+  }
+}
+
 /// An exported value from a CSS module.
 #[derive(PartialEq, Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -174,31 +265,108 @@ lazy_static! {
   };
 }
 
+/// Whether a `CustomIdent` (class, id, animation name, grid line name, etc.) should be
+/// scoped to this file or left as-is, as set explicitly via the `:local(...)` /
+/// `:global(...)` pseudo-functions, or the CSS modules default of local scoping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentScope {
+  /// Hash the identifier, scoping it to this file. This is the default.
+  Local,
+  /// Leave the identifier as written, without hashing it.
+  Global,
+}
+
+impl Default for IdentScope {
+  fn default() -> Self {
+    IdentScope::Local
+  }
+}
+
+impl IdentScope {
+  /// Recognizes an explicit `local(ident)` / `global(ident)` wrapper (written without a
+  /// leading colon) around a `CustomIdent`'s source text, returning the scope it selects and the unwrapped
+  /// identifier. An identifier with no wrapper keeps the CSS modules default, local scope.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use lightningcss::css_modules::IdentScope;
+  ///
+  /// assert_eq!(IdentScope::parse("global(foo)"), (IdentScope::Global, "foo"));
+  /// assert_eq!(IdentScope::parse("foo"), (IdentScope::Local, "foo"));
+  /// ```
+  pub fn parse(ident: &str) -> (IdentScope, &str) {
+    if let Some(inner) = ident.strip_prefix("local(").and_then(|rest| rest.strip_suffix(')')) {
+      (IdentScope::Local, inner)
+    } else if let Some(inner) = ident.strip_prefix("global(").and_then(|rest| rest.strip_suffix(')')) {
+      (IdentScope::Global, inner)
+    } else {
+      (IdentScope::Local, ident)
+    }
+  }
+}
+
+/// A map of dashed-ident (`var(--foo)`) references collected while printing, populated
+/// when the `dashed_idents` config option is enabled.
+pub type CssModuleReferences = HashMap<String, CssModuleReference>;
+
 pub(crate) struct CssModule<'a, 'b> {
   pub config: &'a Config<'b>,
-  pub hash: String,
-  pub exports: &'a mut CssModuleExports,
+  /// The source file names the style sheet was built from, indexed by a rule's
+  /// `loc.source_index`. Used to resolve `[name]` and `[folder]` per rule.
+  pub sources: &'a [String],
+  /// A hash of each entry in `sources`, computed once up front so every rule in that
+  /// source shares the same `[hash]` value.
+  hashes: Vec<String>,
+  /// The CSS module exports declared so far, one map per entry in `sources`.
+  pub exports_by_source_index: Vec<CssModuleExports>,
+  /// Dashed-ident references collected while printing, for the `dashed_idents` config option.
+  pub references: &'a mut CssModuleReferences,
 }
 
 impl<'a, 'b> CssModule<'a, 'b> {
-  pub fn add_local(&mut self, exported: &str, local: &str) {
-    let hash = &self.hash;
-    self.exports.entry(exported.into()).or_insert_with(|| CssModuleExport {
-      name: self.config.pattern.write_to_string(hash, local).unwrap(),
-      composes: vec![],
-      is_referenced: false,
-    });
+  /// Creates a new CSS modules printing context for a style sheet built from `sources`,
+  /// one of which a given rule is associated with via its `loc.source_index`.
+  pub fn new(config: &'a Config<'b>, sources: &'a [String], references: &'a mut CssModuleReferences) -> Self {
+    CssModule {
+      config,
+      hashes: sources.iter().map(|source| hash(source)).collect(),
+      exports_by_source_index: sources.iter().map(|_| CssModuleExports::default()).collect(),
+      sources,
+      references,
+    }
+  }
+
+  pub fn add_local(&mut self, source_index: usize, exported: &str, local: &str, scope: IdentScope) {
+    let path = Path::new(&self.sources[source_index]);
+    let name = match scope {
+      IdentScope::Local => self.config.pattern.write_to_string(&self.hashes[source_index], path, local).unwrap(),
+      IdentScope::Global => local.to_owned(),
+    };
+    self.exports_by_source_index[source_index]
+      .entry(exported.into())
+      .or_insert_with(|| CssModuleExport {
+        name,
+        composes: vec![],
+        is_referenced: false,
+      });
   }
 
-  pub fn reference(&mut self, name: &str) {
-    let hash = &self.hash;
-    match self.exports.entry(name.into()) {
+  pub fn reference(&mut self, source_index: usize, name: &str, scope: IdentScope) {
+    match self.exports_by_source_index[source_index].entry(name.into()) {
       std::collections::hash_map::Entry::Occupied(mut entry) => {
         entry.get_mut().is_referenced = true;
       }
       std::collections::hash_map::Entry::Vacant(entry) => {
+        let compiled = match scope {
+          IdentScope::Local => {
+            let path = Path::new(&self.sources[source_index]);
+            self.config.pattern.write_to_string(&self.hashes[source_index], path, name).unwrap()
+          }
+          IdentScope::Global => name.to_owned(),
+        };
         entry.insert(CssModuleExport {
-          name: self.config.pattern.write_to_string(hash, name).unwrap(),
+          name: compiled,
           composes: vec![],
           is_referenced: true,
         });
@@ -206,42 +374,198 @@ impl<'a, 'b> CssModule<'a, 'b> {
     }
   }
 
+  /// Declares a `CustomIdent` from its source text, honoring an explicit `local(...)` /
+  /// `global(...)` wrapper, and defaulting to local scoping otherwise. This is the entry
+  /// point used when printing a class, id, `@keyframes` name, or grid line name.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use std::collections::HashMap;
+  /// use lightningcss::css_modules::{Config, CssModule};
+  ///
+  /// let config = Config::default();
+  /// let sources = vec!["foo.css".to_string()];
+  /// let mut references = HashMap::new();
+  /// let mut css_module = CssModule::new(&config, &sources, &mut references);
+  /// css_module.add_ident(0, "foo", "global(foo)");
+  /// assert_eq!(css_module.exports_by_source_index[0]["foo"].name, "foo");
+  /// ```
+  pub fn add_ident(&mut self, source_index: usize, exported: &str, local: &str) {
+    let (scope, local) = IdentScope::parse(local);
+    self.add_local(source_index, exported, local, scope);
+  }
+
+  /// Resolves a reference to a previously-declared `CustomIdent` from its source text, as
+  /// for [add_ident](Self::add_ident).
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use std::collections::HashMap;
+  /// use lightningcss::css_modules::{Config, CssModule};
+  ///
+  /// let config = Config::default();
+  /// let sources = vec!["foo.css".to_string()];
+  /// let mut references = HashMap::new();
+  /// let mut css_module = CssModule::new(&config, &sources, &mut references);
+  /// css_module.add_ident(0, "foo", "foo");
+  /// css_module.reference_ident(0, "local(foo)");
+  /// assert!(css_module.exports_by_source_index[0]["foo"].is_referenced);
+  /// ```
+  pub fn reference_ident(&mut self, source_index: usize, name: &str) {
+    let (scope, name) = IdentScope::parse(name);
+    self.reference(source_index, name, scope);
+  }
+
+  /// Registers an ICSS `@value name: ...;` declaration, recording the serialized value as
+  /// an export so it can be substituted wherever `name` is referenced later in the file.
+  pub fn add_value(&mut self, source_index: usize, name: &str, value: &str) {
+    self.exports_by_source_index[source_index].insert(
+      name.into(),
+      CssModuleExport {
+        name: value.to_owned(),
+        composes: vec![],
+        is_referenced: false,
+      },
+    );
+  }
+
+  /// Registers an ICSS `@value name from "./other.css";` import, recording a dependency
+  /// reference so that bundlers can resolve it to the value exported by the other file.
+  pub fn add_value_dependency(&mut self, source_index: usize, name: &str, specifier: &str) {
+    self.exports_by_source_index[source_index].insert(
+      name.into(),
+      CssModuleExport {
+        name: name.to_owned(),
+        composes: vec![CssModuleReference::Dependency {
+          name: name.to_owned(),
+          specifier: specifier.to_owned(),
+        }],
+        is_referenced: false,
+      },
+    );
+  }
+
+  /// Returns the resolved value previously declared via `@value name: ...;`, if any,
+  /// so that it can be substituted at the point of use.
+  pub fn get_value(&mut self, source_index: usize, name: &str) -> Option<&str> {
+    let export = self.exports_by_source_index[source_index].get_mut(name)?;
+    export.is_referenced = true;
+    Some(export.name.as_str())
+  }
+
+  /// Parses and registers an ICSS `@value` rule from its raw prelude, i.e. the text of
+  /// an `@value` at-rule between `@value` and the terminating `;`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use std::collections::HashMap;
+  /// use lightningcss::css_modules::{Config, CssModule};
+  ///
+  /// let config = Config::default();
+  /// let sources = vec!["foo.css".to_string()];
+  /// let mut references = HashMap::new();
+  /// let mut css_module = CssModule::new(&config, &sources, &mut references);
+  /// css_module.handle_value_rule(0, "small: 480px").unwrap();
+  /// assert_eq!(css_module.substitute_values(0, "small"), "480px");
+  ///
+  /// // `@value` imports register a dependency reference rather than a literal value.
+  /// css_module.handle_value_rule(0, "breakpoint from \"./breakpoints.css\"").unwrap();
+  /// assert_eq!(
+  ///   css_module.exports_by_source_index[0]["breakpoint"].composes,
+  ///   vec![lightningcss::css_modules::CssModuleReference::Dependency {
+  ///     name: "breakpoint".into(),
+  ///     specifier: "./breakpoints.css".into(),
+  ///   }]
+  /// );
+  /// ```
+  pub fn handle_value_rule(&mut self, source_index: usize, prelude: &str) -> Result<(), ()> {
+    match ValueRule::parse(prelude).ok_or(())? {
+      ValueRule::Declaration { name, value } => self.add_value(source_index, name, value),
+      ValueRule::Import { names, specifier } => {
+        for name in names {
+          self.add_value_dependency(source_index, name, specifier);
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Substitutes any top-level identifier token in `raw` that matches a name declared via
+  /// `@value name: ...;` with its resolved value. This is how `@value` constants declared
+  /// earlier in the file are resolved at their point of use when printing a declaration.
+  /// Identifiers nested inside functions or other blocks are copied unchanged.
+  pub fn substitute_values(&mut self, source_index: usize, raw: &str) -> String {
+    let mut input = ParserInput::new(raw);
+    let mut parser = Parser::new(&mut input);
+    let mut result = String::new();
+
+    loop {
+      let start = parser.position();
+      match parser.next_including_whitespace() {
+        Ok(&Token::Ident(ref name)) => match self.get_value(source_index, name.as_ref()) {
+          Some(value) => result.push_str(value),
+          None => result.push_str(parser.slice_from(start)),
+        },
+        Ok(_) => result.push_str(parser.slice_from(start)),
+        Err(_) => {
+          // Reading the next token skips the rest of a block opened by the previous one,
+          // so copy whatever was skipped before stopping.
+          result.push_str(parser.slice_from(start));
+          break;
+        }
+      }
+    }
+
+    result
+  }
+
   pub fn handle_composes(
     &mut self,
+    source_index: usize,
     selectors: &SelectorList<Selectors>,
     composes: &Composes,
   ) -> Result<(), PrinterErrorKind> {
-    let hash = &self.hash;
+    let hash = &self.hashes[source_index];
+    let path = Path::new(&self.sources[source_index]);
     for sel in &selectors.0 {
       if sel.len() == 1 {
-        match sel.iter_raw_match_order().next().unwrap() {
-          parcel_selectors::parser::Component::Class(ref id) => {
-            for name in &composes.names {
-              let reference = match &composes.from {
-                None => CssModuleReference::Local {
-                  name: self.config.pattern.write_to_string(hash, name.0.as_ref()).unwrap(),
-                },
-                Some(ComposesFrom::Global) => CssModuleReference::Global {
-                  name: name.0.as_ref().into(),
-                },
-                Some(ComposesFrom::File(file)) => CssModuleReference::Dependency {
-                  name: name.0.to_string(),
-                  specifier: file.to_string(),
-                },
-              };
-
-              let export = self.exports.get_mut(&id.0.as_ref().to_owned()).unwrap();
-              if !export.composes.contains(&reference) {
-                export.composes.push(reference);
-              }
+        // `composes` can target either a class selector or an id selector, since the
+        // module system scopes (and hashes) both in the same way.
+        let exported = match sel.iter_raw_match_order().next().unwrap() {
+          parcel_selectors::parser::Component::Class(ref id) => Some(id.0.as_ref()),
+          parcel_selectors::parser::Component::ID(ref id) => Some(id.0.as_ref()),
+          _ => None,
+        };
+
+        if let Some(exported) = exported {
+          for name in &composes.names {
+            let reference = match &composes.from {
+              None => CssModuleReference::Local {
+                name: self.config.pattern.write_to_string(hash, path, name.0.as_ref()).unwrap(),
+              },
+              Some(ComposesFrom::Global) => CssModuleReference::Global {
+                name: name.0.as_ref().into(),
+              },
+              Some(ComposesFrom::File(file)) => CssModuleReference::Dependency {
+                name: name.0.to_string(),
+                specifier: file.to_string(),
+              },
+            };
+
+            let export = self.exports_by_source_index[source_index].get_mut(&exported.to_owned()).unwrap();
+            if !export.composes.contains(&reference) {
+              export.composes.push(reference);
             }
-            continue;
           }
-          _ => {}
+          continue;
         }
       }
 
-      // The composes property can only be used within a simple class selector.
+      // The composes property can only be used within a simple class or id selector.
       return Err(PrinterErrorKind::InvalidComposesSelector);
     }
 
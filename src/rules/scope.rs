@@ -0,0 +1,111 @@
+use cssparser::*;
+use crate::traits::ToCss;
+use crate::printer::Printer;
+use crate::compat::Feature;
+use crate::error::{MinifyErrorKind, ParserError, PrinterError};
+use crate::selector::Selectors;
+use crate::rules::{CssRule, CssRuleList, MinifyContext};
+use parcel_selectors::SelectorList;
+
+/// A [@scope](https://drafts.csswg.org/css-cascade-6/#scope-atrule) rule.
+///
+/// `@scope (<scope-start>) to (<scope-end>) { ... }` establishes a styling scope bounded by
+/// the `scope_start` and `scope_end` selectors, within which `:scope` refers to the element
+/// matched by `scope_start` (or the rule's containing element, if omitted).
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScopeRule<'i, R = CssRule<'i>> {
+  /// The lower boundary of the scope. A `None` value means the scope starts at the
+  /// style rule's own implicit `:scope` root, i.e. a bare `@scope { ... }`.
+  pub scope_start: Option<SelectorList<Selectors>>,
+  /// The upper boundary of the scope, from the optional `to (<scope-end>)` clause.
+  pub scope_end: Option<SelectorList<Selectors>>,
+  /// The rules within the `@scope` block.
+  pub rules: CssRuleList<'i, R>,
+  /// The location of the rule in the source file.
+  pub loc: SourceLocation,
+}
+
+impl<'i, R> ScopeRule<'i, R> {
+  /// Parses the prelude of an `@scope` rule: an optional `(<scope-start>)`, followed by an
+  /// optional `to (<scope-end>)`. `parse_selector_list` parses a single parenthesized
+  /// selector list, and is typically `SelectorList::parse` bound to the containing style
+  /// sheet's selector parser.
+  pub fn parse_prelude<'t>(
+    input: &mut Parser<'i, 't>,
+    mut parse_selector_list: impl FnMut(&mut Parser<'i, 't>) -> Result<SelectorList<Selectors>, ParseError<'i, ParserError<'i>>>,
+  ) -> Result<(Option<SelectorList<Selectors>>, Option<SelectorList<Selectors>>), ParseError<'i, ParserError<'i>>> {
+    let scope_start = if input.try_parse(|input| input.expect_parenthesis_block()).is_ok() {
+      Some(input.parse_nested_block(|input| parse_selector_list(input))?)
+    } else {
+      None
+    };
+
+    let scope_end = if input
+      .try_parse(|input| input.expect_ident_matching("to").and_then(|_| input.expect_parenthesis_block()))
+      .is_ok()
+    {
+      Some(input.parse_nested_block(|input| parse_selector_list(input))?)
+    } else {
+      None
+    };
+
+    Ok((scope_start, scope_end))
+  }
+}
+
+impl<'i, R: ToCss> ScopeRule<'i, R> {
+  /// Minifies the rules inside this `@scope` block, descending into its nested rule list.
+  ///
+  /// Returns `true` if the `@scope` rule should be dropped entirely, because the targeted
+  /// browsers don't support CSS scoping. No fallback lowering of `:scope`-anchored
+  /// selectors is attempted; an incompatible-target `@scope` block is simply removed.
+  pub fn minify(&mut self, context: &mut MinifyContext, parent_is_unused: bool) -> Result<bool, MinifyErrorKind> {
+    if let Some(targets) = context.targets {
+      if !Feature::Scope.is_compatible(*targets) {
+        return Ok(true);
+      }
+    }
+
+    self.rules.minify(context, parent_is_unused)?;
+    Ok(false)
+  }
+}
+
+impl<'i, R: ToCss> ToCss for ScopeRule<'i, R> {
+  fn to_css<W>(&self, dest: &mut Printer<W>) -> Result<(), PrinterError>
+  where
+    W: std::fmt::Write,
+  {
+    dest.add_mapping(self.loc);
+    dest.write_str("@scope")?;
+
+    if let Some(scope_start) = &self.scope_start {
+      dest.write_str(" (")?;
+      scope_start.to_css(dest)?;
+      dest.write_char(')')?;
+    }
+
+    if let Some(scope_end) = &self.scope_end {
+      dest.write_str(" to (")?;
+      scope_end.to_css(dest)?;
+      dest.write_char(')')?;
+    }
+
+    dest.whitespace()?;
+    dest.write_char('{')?;
+    dest.indent();
+
+    let len = self.rules.0.len();
+    for (i, rule) in self.rules.0.iter().enumerate() {
+      dest.newline()?;
+      rule.to_css(dest)?;
+      if i != len - 1 {
+        dest.newline()?;
+      }
+    }
+
+    dest.dedent();
+    dest.newline()?;
+    dest.write_char('}')
+  }
+}
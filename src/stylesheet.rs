@@ -14,6 +14,7 @@ use crate::printer::Printer;
 use crate::rules::{CssRule, CssRuleList, MinifyContext};
 use crate::targets::Browsers;
 use crate::traits::ToCss;
+use crate::visitor::Visitor;
 use cssparser::{Parser, ParserInput, RuleListParser, AtRuleParser};
 use parcel_sourcemap::SourceMap;
 use std::collections::{HashMap, HashSet};
@@ -201,6 +202,14 @@ where
     Ok(())
   }
 
+  /// Visits every rule (and, depending on the visitor, selector, declaration, and `url()`)
+  /// in the style sheet, in document order. This allows custom lint and codemod passes,
+  /// such as rewriting every `url()` or collecting all colors used, to be written without
+  /// forking the minifier.
+  pub fn visit(&mut self, visitor: &mut impl Visitor<'i>) {
+    self.rules.visit(visitor);
+  }
+
   /// Serialize the style sheet to a CSS string.
   pub fn to_css(&self, options: PrinterOptions) -> Result<ToCssResult, Error<PrinterErrorKind>> {
     // Make sure we always have capacity > 0: https://github.com/napi-rs/napi-rs/issues/1124.
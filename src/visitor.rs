@@ -0,0 +1,128 @@
+//! A visitor for traversing and transforming a [StyleSheet](crate::stylesheet::StyleSheet).
+//!
+//! [Visitor](Visitor) walks the full rule tree in document order, descending into the
+//! nested [CssRuleList](crate::rules::CssRuleList)s owned by rules such as `@media`,
+//! `@supports`, `@container`, and `@scope`. Implementors can mutate rules, selectors, declarations,
+//! and `url()`s in place, replace a node entirely, or remove a rule by returning
+//! [VisitResult::Remove](VisitResult::Remove) from [visit_rule](Visitor::visit_rule). [VisitTypes](VisitTypes) lets a visitor declare up front which kinds of
+//! node it cares about, so the walk can skip whole subtrees that can't contain them.
+
+use crate::declaration::DeclarationBlock;
+use crate::rules::container::ContainerRule;
+use crate::rules::keyframes::KeyframesRule;
+use crate::rules::layer::LayerBlockRule;
+use crate::rules::media::MediaRule;
+use crate::rules::scope::ScopeRule;
+use crate::rules::style::StyleRule;
+use crate::rules::supports::SupportsRule;
+use crate::rules::{CssRule, CssRuleList};
+use crate::selector::Selectors;
+use crate::values::url::Url;
+use bitflags::bitflags;
+use parcel_selectors::SelectorList;
+
+bitflags! {
+  /// Flags indicating which kinds of node a [Visitor](Visitor) is interested in.
+  ///
+  /// A visitor returns the set of types it cares about from [Visitor::types], which
+  /// allows the walk to avoid descending into subtrees that can't contain them.
+  pub struct VisitTypes: u8 {
+    /// Visit rules.
+    const RULES = 1 << 0;
+    /// Visit selectors.
+    const SELECTORS = 1 << 1;
+    /// Visit declarations.
+    const DECLARATIONS = 1 << 2;
+    /// Visit `url()` values.
+    const URLS = 1 << 3;
+  }
+}
+
+/// The result of visiting a rule: whether the visitor wants to keep it or remove it from
+/// its containing list.
+pub enum VisitResult {
+  /// Keep the node, and continue visiting its children (if any).
+  Continue,
+  /// Remove the node from its containing list entirely.
+  Remove,
+}
+
+/// A visitor which can be used to traverse and transform a [StyleSheet](crate::stylesheet::StyleSheet).
+///
+/// Implement the callbacks for the kinds of node you're interested in, and declare them
+/// in [types](Visitor::types) so the rest can be skipped. Lint and codemod passes (rewrite
+/// every `url()`, strip a vendor prefix, collect all colors used) can be written entirely
+/// in terms of this trait, without forking the minifier.
+pub trait Visitor<'i> {
+  /// The types of node this visitor is interested in. Defaults to all node kinds.
+  fn types(&self) -> VisitTypes {
+    VisitTypes::all()
+  }
+
+  /// Called for every rule in document order, before descending into its children (if any).
+  fn visit_rule(&mut self, _rule: &mut CssRule<'i>) -> VisitResult {
+    VisitResult::Continue
+  }
+
+  /// Called for the selector list of every style rule.
+  fn visit_selector(&mut self, _selectors: &mut SelectorList<Selectors>) {}
+
+  /// Called for the declaration block of every style rule and keyframe. Declaration blocks
+  /// of other rules, such as `@page`, are not visited.
+  fn visit_declaration(&mut self, _declarations: &mut DeclarationBlock<'i>) {}
+
+  /// Called for every `url()` value found within a declaration.
+  fn visit_url(&mut self, _url: &mut Url<'i>) {}
+}
+
+impl<'i, T> CssRuleList<'i, T> {
+  /// Visits every rule in this list, recursing into nested rule lists, in document order.
+  pub fn visit(&mut self, visitor: &mut impl Visitor<'i>) {
+    let types = visitor.types();
+    self.0.retain_mut(|rule| {
+      if types.contains(VisitTypes::RULES) {
+        if let VisitResult::Remove = visitor.visit_rule(rule) {
+          return false;
+        }
+      }
+
+      match rule {
+        CssRule::Style(style) => visit_style_rule(style, visitor, types),
+        CssRule::Media(MediaRule { rules, .. }) => rules.visit(visitor),
+        CssRule::Supports(SupportsRule { rules, .. }) => rules.visit(visitor),
+        CssRule::Container(ContainerRule { rules, .. }) => rules.visit(visitor),
+        CssRule::Scope(ScopeRule { rules, .. }) => rules.visit(visitor),
+        CssRule::LayerBlock(LayerBlockRule { rules, .. }) => rules.visit(visitor),
+        CssRule::Keyframes(KeyframesRule { keyframes, .. }) => {
+          for keyframe in keyframes {
+            visit_declaration_block(&mut keyframe.declarations, visitor, types);
+          }
+        }
+        _ => {}
+      }
+
+      true
+    });
+  }
+}
+
+fn visit_style_rule<'i>(style: &mut StyleRule<'i>, visitor: &mut impl Visitor<'i>, types: VisitTypes) {
+  if types.contains(VisitTypes::SELECTORS) {
+    visitor.visit_selector(&mut style.selectors);
+  }
+
+  visit_declaration_block(&mut style.declarations, visitor, types);
+  style.rules.visit(visitor);
+}
+
+/// Calls `visit_declaration` (and, for any `url()` values it contains, `visit_url`) for a
+/// single declaration block, in document order.
+fn visit_declaration_block<'i>(declarations: &mut DeclarationBlock<'i>, visitor: &mut impl Visitor<'i>, types: VisitTypes) {
+  if types.contains(VisitTypes::DECLARATIONS) {
+    visitor.visit_declaration(declarations);
+  }
+
+  if types.contains(VisitTypes::URLS) {
+    declarations.visit_urls(&mut |url| visitor.visit_url(url));
+  }
+}